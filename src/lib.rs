@@ -1,6 +1,6 @@
 use anyhow::{bail, Result};
 use async_std::{sync, task};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use shrinkwraprs::Shrinkwrap;
 use std::{
     convert::TryFrom,
@@ -69,6 +69,9 @@ pub enum GitProvider {
     GitHub,
     GitLab,
     Bitbucket,
+    // A self-hosted host (anything containing a dot), reachable only via the clone backend since it
+    // has no codeload-style archive endpoint.
+    Generic(String),
 }
 
 #[derive(Error, Debug)]
@@ -85,42 +88,275 @@ impl FromStr for GitProvider {
             "github" => Ok(GitProvider::GitHub),
             "gitlab" => Ok(GitProvider::GitLab),
             "bitbucket" => Ok(GitProvider::Bitbucket),
+            // Treat anything that looks like a hostname as a self-hosted Git server.
+            host if host.contains('.') => Ok(GitProvider::Generic(host.into())),
             _ => Err(Self::Err::UnknownProvider(s.into())),
         }
     }
 }
 
-// git_ref can be a branch name, tag name, or commit hash.
+impl GitProvider {
+    // The canonical short name of the provider (or the bare host for a self-hosted server), used to
+    // build a plugin’s fully-qualified identity.
+    fn id(&self) -> &str {
+        match self {
+            GitProvider::GitHub => "github",
+            GitProvider::GitLab => "gitlab",
+            GitProvider::Bitbucket => "bitbucket",
+            GitProvider::Generic(host) => host,
+        }
+    }
+}
+
+// git_ref can be a branch name, tag name, or commit hash. When it is elided in the config it is
+// ‘None’ and the provider’s default branch is resolved on demand.
 #[derive(Deserialize)]
 #[serde(try_from = "String")]
 pub struct GitRepo {
     provider: GitProvider,
     user: String,
     repo: String,
-    git_ref: String,
+    git_ref: Option<String>,
 }
 
-impl TryFrom<&GitRepo> for Url {
-    type Error = url::ParseError;
-
-    fn try_from(gr: &GitRepo) -> Result<Self, Self::Error> {
-        Url::parse(&match gr.provider {
+impl GitRepo {
+    // Build the codeload/archive URL for a concrete, already-resolved ref.
+    fn archive_url(&self, git_ref: &str) -> Result<Url, url::ParseError> {
+        Url::parse(&match self.provider {
             GitProvider::GitHub => format!(
                 "https://codeload.github.com/{}/{}/tar.gz/{}",
-                gr.user, gr.repo, gr.git_ref
+                self.user, self.repo, git_ref
             ),
             GitProvider::GitLab => format!(
                 "https://gitlab.com/{0}/{1}/-/archive/{2}/{0}-{2}.tar.gz",
-                gr.user, gr.repo, gr.git_ref
+                self.user, self.repo, git_ref
             ),
             GitProvider::Bitbucket => format!(
                 "https://bitbucket.org/{}/{}/get/{}.tar.gz",
-                gr.user, gr.repo, gr.git_ref
+                self.user, self.repo, git_ref
             ),
+            // Self-hosted hosts have no archive endpoint; they must use ‘method: clone’, which is
+            // enforced by a guard in ‘Plugin::install_tarball’ before this conversion runs.
+            GitProvider::Generic(_) => return Err(url::ParseError::RelativeUrlWithoutBase),
         })
     }
 }
 
+impl TryFrom<&GitRepo> for Url {
+    type Error = url::ParseError;
+
+    fn try_from(gr: &GitRepo) -> Result<Self, Self::Error> {
+        // Only usable once a ref is known; callers needing default-branch resolution go through
+        // ‘GitRepo::resolve_ref’ and ‘archive_url’ instead.
+        let git_ref = gr
+            .git_ref
+            .as_deref()
+            .ok_or(url::ParseError::RelativeUrlWithoutBase)?;
+        gr.archive_url(git_ref)
+    }
+}
+
+impl GitRepo {
+    // The HTTPS clone URL for the native git backend. Unlike the archive URL this works for every
+    // provider, including self-hosted hosts.
+    fn clone_url(&self) -> String {
+        match &self.provider {
+            GitProvider::GitHub => format!("https://github.com/{}/{}.git", self.user, self.repo),
+            GitProvider::GitLab => format!("https://gitlab.com/{}/{}.git", self.user, self.repo),
+            GitProvider::Bitbucket => {
+                format!("https://bitbucket.org/{}/{}.git", self.user, self.repo)
+            }
+            GitProvider::Generic(host) => {
+                format!("https://{}/{}/{}.git", host, self.user, self.repo)
+            }
+        }
+    }
+
+    // A stable, fully-qualified identity (provider/user/repo) used to key the lockfile and the log
+    // file, so that two plugins sharing a bare repo name from different users or providers do not
+    // collide.
+    fn identity(&self) -> String {
+        format!("{}/{}/{}", self.provider.id(), self.user, self.repo)
+    }
+
+    // Resolve the ref to use: the one given in the config, or the repository’s default branch
+    // queried from the provider. Resolutions are memoised in ‘cache’ so that parallel installs of
+    // the same repo sharing one cache only make the request once.
+    async fn resolve_ref(&self, cache: &RefCache) -> Result<String> {
+        if let Some(git_ref) = &self.git_ref {
+            return Ok(git_ref.clone());
+        }
+
+        let key = self.clone_url();
+
+        // Hold the lock across the request so a parallel install of the same repo waits for this
+        // lookup instead of making its own.
+        let mut memo = cache.lock().await;
+        if let Some(resolved) = memo.get(&key) {
+            return Ok(resolved.clone());
+        }
+
+        let default_branch = self.fetch_default_branch().await?;
+        memo.insert(key, default_branch.clone());
+
+        Ok(default_branch)
+    }
+
+    async fn fetch_default_branch(&self) -> Result<String> {
+        use anyhow::Context;
+
+        let context = || format!("failed to resolve default branch for {}/{}", self.user, self.repo);
+
+        // GitHub and Bitbucket require a User-Agent header on API requests.
+        let request = |url: String| surf::get(url).set_header("User-Agent", "strand");
+
+        match &self.provider {
+            GitProvider::GitHub => {
+                let meta: GitHubMeta = request(format!(
+                    "https://api.github.com/repos/{}/{}",
+                    self.user, self.repo
+                ))
+                .recv_json()
+                .await
+                .map_err(|e| anyhow::anyhow!(e))
+                .with_context(context)?;
+                Ok(meta.default_branch)
+            }
+            GitProvider::GitLab => {
+                let meta: GitLabMeta = request(format!(
+                    "https://gitlab.com/api/v4/projects/{}%2F{}",
+                    self.user, self.repo
+                ))
+                .recv_json()
+                .await
+                .map_err(|e| anyhow::anyhow!(e))
+                .with_context(context)?;
+                Ok(meta.default_branch)
+            }
+            GitProvider::Bitbucket => {
+                let meta: BitbucketMeta = request(format!(
+                    "https://api.bitbucket.org/2.0/repositories/{}/{}",
+                    self.user, self.repo
+                ))
+                .recv_json()
+                .await
+                .map_err(|e| anyhow::anyhow!(e))
+                .with_context(context)?;
+                Ok(meta.mainbranch.name)
+            }
+            // Self-hosted hosts only ever go through the clone backend, where git itself picks the
+            // remote’s default branch when no ref is checked out.
+            GitProvider::Generic(_) => {
+                bail!("cannot resolve a default branch for a self-hosted host without cloning")
+            }
+        }
+    }
+
+    // Resolve the ref to the commit hash to download and pin. A ref that is already a full commit
+    // hash is immutable and needs no lookup, so we short-circuit it; otherwise we resolve the
+    // default branch (when elided) and then ask the provider which commit the ref points at. A
+    // failing commit lookup (rate-limited or blocked) falls back to the literal ref, which the
+    // codeload/archive endpoints still accept, so a plugin with a ref given in the config installs
+    // from a reachable archive host alone. An elided ref still needs the default-branch lookup,
+    // and its failure is an error.
+    async fn resolve_commit(&self, cache: &RefCache) -> Result<String> {
+        let git_ref = self.resolve_ref(cache).await?;
+
+        if is_commit_hash(&git_ref) {
+            return Ok(git_ref);
+        }
+
+        Ok(self.fetch_commit(&git_ref).await.unwrap_or(git_ref))
+    }
+
+    // Pin a ref (branch, tag or hash) to the exact commit it points at, so the lockfile records a
+    // commit hash rather than a moving branch name — a moved branch or tag then resolves to a
+    // different commit under an unchanged lock entry and is caught.
+    async fn fetch_commit(&self, git_ref: &str) -> Result<String> {
+        use anyhow::Context;
+
+        let context = || format!("failed to resolve commit for {}/{}", self.user, self.repo);
+        let request = |url: String| surf::get(url).set_header("User-Agent", "strand");
+
+        match &self.provider {
+            GitProvider::GitHub => {
+                let commit: GitHubCommit = request(format!(
+                    "https://api.github.com/repos/{}/{}/commits/{}",
+                    self.user, self.repo, git_ref
+                ))
+                .recv_json()
+                .await
+                .map_err(|e| anyhow::anyhow!(e))
+                .with_context(context)?;
+                Ok(commit.sha)
+            }
+            GitProvider::GitLab => {
+                let commit: GitLabCommit = request(format!(
+                    "https://gitlab.com/api/v4/projects/{}%2F{}/repository/commits/{}",
+                    self.user, self.repo, git_ref
+                ))
+                .recv_json()
+                .await
+                .map_err(|e| anyhow::anyhow!(e))
+                .with_context(context)?;
+                Ok(commit.id)
+            }
+            GitProvider::Bitbucket => {
+                let commit: BitbucketCommit = request(format!(
+                    "https://api.bitbucket.org/2.0/repositories/{}/{}/commit/{}",
+                    self.user, self.repo, git_ref
+                ))
+                .recv_json()
+                .await
+                .map_err(|e| anyhow::anyhow!(e))
+                .with_context(context)?;
+                Ok(commit.hash)
+            }
+            GitProvider::Generic(_) => {
+                bail!("cannot resolve a commit for a self-hosted host without cloning")
+            }
+        }
+    }
+}
+
+// Shared, per-run memoisation of default-branch lookups, keyed by clone URL.
+type RefCache = std::sync::Arc<sync::Mutex<std::collections::HashMap<String, String>>>;
+
+#[derive(Deserialize)]
+struct GitHubMeta {
+    default_branch: String,
+}
+
+#[derive(Deserialize)]
+struct GitLabMeta {
+    default_branch: String,
+}
+
+#[derive(Deserialize)]
+struct BitbucketMeta {
+    mainbranch: BitbucketBranch,
+}
+
+#[derive(Deserialize)]
+struct BitbucketBranch {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct GitHubCommit {
+    sha: String,
+}
+
+#[derive(Deserialize)]
+struct GitLabCommit {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct BitbucketCommit {
+    hash: String,
+}
+
 impl fmt::Display for GitRepo {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.repo)
@@ -163,20 +399,18 @@ impl FromStr for GitRepo {
 
         // When the ‘:’ signifier for a Git reference is found, the part preceding it must be the
         // repo name and the part after the Git reference. If it is not found, the rest of ‘input’
-        // must be the repo name, in this case using ‘master’ as the default Git reference.
-        //
-        // FIXME: Some repos have something different to ‘master’ as their default branch. Handle
-        // this somehow.
+        // must be the repo name and the reference is left unspecified, to be resolved to the
+        // repository’s real default branch at install time.
         let (repo, git_ref) = match split_on_pattern(&input[i..], ":", &mut i) {
-            Some(repo) => (repo, &input[i..]),
-            None => (&input[i..], "master"),
+            Some(repo) => (repo, Some(input[i..].to_string())),
+            None => (&input[i..], None),
         };
 
         Ok(Self {
             provider,
             user: user.into(),
             repo: repo.into(),
-            git_ref: git_ref.into(),
+            git_ref,
         })
     }
 }
@@ -208,7 +442,9 @@ impl FromStr for ArchivePlugin {
 
 enum InstallStateKind {
     Downloading,
+    Cached,
     Extracting,
+    Building,
     Installed,
     Retry(u32),
     Error(anyhow::Error),
@@ -220,7 +456,9 @@ impl fmt::Display for InstallStateKind {
 
         match self {
             InstallStateKind::Downloading => write!(f, "{}", "Downloading".cyan().bold()),
+            InstallStateKind::Cached => write!(f, "    {}", "Cached".green().bold()),
             InstallStateKind::Extracting => write!(f, " {}", "Extracting".blue().bold()),
+            InstallStateKind::Building => write!(f, "  {}", "Building".magenta().bold()),
             InstallStateKind::Installed => write!(f, "✓ {}", "Installed".green().bold()),
             InstallStateKind::Retry(i) => {
                 write!(f, "      {}: attempt #{} of", "Retry".yellow().bold(), i)
@@ -230,6 +468,22 @@ impl fmt::Display for InstallStateKind {
     }
 }
 
+impl InstallStateKind {
+    // A plain, uncoloured description of the transition for the per-plugin log file.
+    fn log_line(&self) -> String {
+        match self {
+            InstallStateKind::Downloading => "Downloading".into(),
+            InstallStateKind::Cached => "Cached (served from local cache)".into(),
+            InstallStateKind::Extracting => "Extracting".into(),
+            InstallStateKind::Building => "Building (running post-install hook)".into(),
+            InstallStateKind::Installed => "Installed".into(),
+            InstallStateKind::Retry(i) => format!("Retry: download attempt #{}", i),
+            // ‘{:#}’ renders the whole anyhow error chain on one line.
+            InstallStateKind::Error(e) => format!("Error: {:#}", e),
+        }
+    }
+}
+
 struct InstallState {
     status: InstallStateKind,
     name: String,
@@ -242,28 +496,184 @@ impl fmt::Display for InstallState {
 }
 
 #[derive(Deserialize)]
-pub enum Plugin {
+pub enum Source {
     Git(GitRepo),
     Archive(ArchivePlugin),
 }
 
-impl fmt::Display for Plugin {
+impl fmt::Display for Source {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Plugin::Git(plugin) => write!(f, "{}", plugin),
-            Plugin::Archive(plugin) => write!(f, "{}", plugin),
+            Source::Git(plugin) => write!(f, "{}", plugin),
+            Source::Archive(plugin) => write!(f, "{}", plugin),
+        }
+    }
+}
+
+impl TryFrom<&Source> for Url {
+    type Error = url::ParseError;
+
+    fn try_from(s: &Source) -> Result<Self, Self::Error> {
+        match s {
+            Source::Git(gr) => Url::try_from(gr),
+            Source::Archive(a) => Ok((*a).clone()),
+        }
+    }
+}
+
+impl Source {
+    // The configured ref spec recorded in the lockfile’s ‘spec’ field: a Git repo’s ref as written
+    // in the config (not the commit it resolves to) or, for a bare archive, its download URL.
+    fn resolved(&self) -> String {
+        match self {
+            // An elided ref has no spec of its own; it compares as empty. Whether such a
+            // default-branch plugin needs refreshing is decided by re-resolving its commit in
+            // ‘update_plugins’, not by this string.
+            Source::Git(gr) => gr.git_ref.clone().unwrap_or_default(),
+            Source::Archive(a) => a.to_string(),
+        }
+    }
+
+    // The commit a moving ref currently points at, used by ‘update_plugins’ to notice that a
+    // branch, tag or default branch has advanced since it was locked. Returns ‘None’ when there is
+    // nothing to re-resolve — a bare archive, a self-hosted clone-only host, or a transient
+    // metadata-API failure — in which case the plugin is left untouched.
+    async fn resolve_current_commit(&self, cache: &RefCache) -> Option<String> {
+        match self {
+            Source::Git(GitRepo {
+                provider: GitProvider::Generic(_),
+                ..
+            }) => None,
+            Source::Git(gr) => {
+                // Unlike ‘resolve_commit’, do not fall back to the literal ref on a failed lookup:
+                // a branch name never equals the locked hash and would force a reinstall.
+                let git_ref = gr.resolve_ref(cache).await.ok()?;
+                if is_commit_hash(&git_ref) {
+                    return Some(git_ref);
+                }
+                gr.fetch_commit(&git_ref).await.ok()
+            }
+            Source::Archive(_) => None,
+        }
+    }
+
+    // A stable, fully-qualified identity used to key the lockfile and log file. Unlike ‘Display’
+    // (which is the bare repo name for presentation) this disambiguates repos that share a name.
+    fn identity(&self) -> String {
+        match self {
+            Source::Git(gr) => gr.identity(),
+            Source::Archive(a) => a.to_string(),
+        }
+    }
+}
+
+impl FromStr for Source {
+    type Err = PluginParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ArchivePlugin::from_str(s)
+            .map(Source::Archive)
+            .or_else(|_| GitRepo::from_str(s).map(Source::Git).map_err(|e| e.into()))
+    }
+}
+
+// How a plugin’s files are acquired: by downloading a tarball (the default) or by cloning the
+// repository with git, which preserves ‘.git’ and pulls in submodules.
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Method {
+    Tarball,
+    Clone,
+}
+
+impl Default for Method {
+    fn default() -> Self {
+        Method::Tarball
+    }
+}
+
+impl Method {
+    // The method’s lowercase name, recorded in the lockfile so a switch between backends can be
+    // detected and the old directory pruned.
+    fn name(&self) -> &'static str {
+        match self {
+            Method::Tarball => "tarball",
+            Method::Clone => "clone",
         }
     }
 }
 
+/// A plugin as it appears in the config: an acquisition [`Source`], an optional shell command run
+/// after extraction to build any native component, and the acquisition [`Method`].
+#[derive(Deserialize)]
+#[serde(from = "PluginDef")]
+pub struct Plugin {
+    source: Source,
+    build: Option<String>,
+    method: Method,
+}
+
+// A config entry may be either a bare source (the common case) or a table pairing that source with
+// a build hook. ‘do’ and ‘run’ are accepted as aliases for ‘build’ to match the spellings other
+// plugin managers use.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PluginDef {
+    Bare(Source),
+    Full {
+        #[serde(flatten)]
+        source: Source,
+        #[serde(default, alias = "do", alias = "run")]
+        build: Option<String>,
+        #[serde(default)]
+        method: Method,
+    },
+}
+
+impl From<PluginDef> for Plugin {
+    fn from(def: PluginDef) -> Self {
+        match def {
+            PluginDef::Bare(source) => Self {
+                source,
+                build: None,
+                method: Method::default(),
+            },
+            PluginDef::Full {
+                source,
+                build,
+                method,
+            } => Self {
+                source,
+                build,
+                method,
+            },
+        }
+    }
+}
+
+impl fmt::Display for Plugin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl Plugin {
+    // The plugin’s fully-qualified identity, used to key the lockfile and its log file.
+    fn identity(&self) -> String {
+        self.source.identity()
+    }
+
+    // The name of the acquisition method, recorded in the lockfile for change detection.
+    fn method_name(&self) -> &'static str {
+        self.method.name()
+    }
+}
+
 impl TryFrom<&Plugin> for Url {
     type Error = url::ParseError;
 
     fn try_from(p: &Plugin) -> Result<Self, Self::Error> {
-        match p {
-            Plugin::Git(gr) => Url::try_from(gr),
-            Plugin::Archive(a) => Ok((*a).clone()),
-        }
+        Url::try_from(&p.source)
     }
 }
 
@@ -279,9 +689,11 @@ impl FromStr for Plugin {
     type Err = PluginParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        ArchivePlugin::from_str(s)
-            .map(Plugin::Archive)
-            .or_else(|_| GitRepo::from_str(s).map(Plugin::Git).map_err(|e| e.into()))
+        Ok(Self {
+            source: Source::from_str(s)?,
+            build: None,
+            method: Method::default(),
+        })
     }
 }
 
@@ -311,24 +723,232 @@ async fn recv_bytes_retry(
     }
 }
 
+// The integrity pin carried over from a previous install: the commit it was locked at together with
+// the SRI digest of that commit’s archive. It is only enforced when a fresh install re-downloads the
+// very same commit, so that a legitimate ref bump (which resolves to a different commit) is recorded
+// as a new pin rather than mistaken for tampering.
+struct ExpectedPin {
+    resolved: String,
+    integrity: String,
+}
+
 impl Plugin {
-    async fn install(&self, path: PathBuf, s: sync::Sender<InstallState>) -> Result<()> {
+    async fn install(
+        &self,
+        path: PathBuf,
+        s: sync::Sender<InstallState>,
+        expected: Option<ExpectedPin>,
+        cache: &RefCache,
+    ) -> Result<LockEntry> {
+        match self.method {
+            Method::Tarball => self.install_tarball(path, s, expected, cache).await,
+            Method::Clone => self.install_clone(path, s).await,
+        }
+    }
+
+    // Clone the repository with git at the requested ref, recursing into submodules. This keeps a
+    // real working tree (including ‘.git’) and so handles plugins that a flat archive cannot.
+    async fn install_clone(
+        &self,
+        path: PathBuf,
+        s: sync::Sender<InstallState>,
+    ) -> Result<LockEntry> {
         use anyhow::Context;
+        use std::process::Command;
 
         let name = self.to_string();
 
+        let gr = match &self.source {
+            Source::Git(gr) => gr,
+            Source::Archive(_) => {
+                bail!("the ‘clone’ method is only supported for Git plugins, not archives")
+            }
+        };
+
         s.send(InstallState {
             status: InstallStateKind::Downloading,
             name: name.clone(),
         })
         .await;
 
-        let recv_bytes = recv_bytes_retry(&Url::try_from(self)?.as_str(), &s, &name)
-            .await
-            .with_context(|| "failed downloading plugin")?;
+        let dest = path.join(&gr.repo);
+
+        // ‘git clone’ refuses a destination that already exists and is non-empty, so a re-run
+        // (including the incremental ‘update’ flow) would fail on the second install. Clear any
+        // previous checkout first so the clone always starts from a clean directory.
+        if dest.exists() {
+            remove_path(&dest).await?;
+        }
+
+        let run = |cmd: &mut Command, what: &str| -> Result<()> {
+            let output = cmd
+                .output()
+                .with_context(|| format!("failed to spawn git to {}", what))?;
+            if !output.status.success() {
+                bail!(
+                    "git failed to {}: {}",
+                    what,
+                    String::from_utf8_lossy(&output.stderr),
+                );
+            }
+            Ok(())
+        };
+
+        run(
+            Command::new("git")
+                .arg("clone")
+                .arg("--recurse-submodules")
+                .arg(gr.clone_url())
+                .arg(&dest),
+            "clone repository",
+        )?;
+        // With no explicit ref we stay on whatever branch the clone checked out (the remote’s
+        // default), so only check out when one was requested.
+        if let Some(git_ref) = &gr.git_ref {
+            run(
+                Command::new("git")
+                    .arg("-C")
+                    .arg(&dest)
+                    .arg("checkout")
+                    .arg(git_ref),
+                "check out the requested ref",
+            )?;
+        }
+        run(
+            Command::new("git")
+                .arg("-C")
+                .arg(&dest)
+                .args(&["submodule", "update", "--init", "--recursive"]),
+            "update submodules",
+        )?;
+
+        // Record the exact commit we ended up on so the lockfile pins a hash, not a branch name.
+        let rev = Command::new("git")
+            .arg("-C")
+            .arg(&dest)
+            .args(&["rev-parse", "HEAD"])
+            .output()
+            .with_context(|| "failed to resolve cloned commit")?;
+        let resolved = String::from_utf8_lossy(&rev.stdout).trim().to_string();
+
+        s.send(InstallState {
+            status: InstallStateKind::Installed,
+            name,
+        })
+        .await;
+
+        Ok(LockEntry {
+            spec: self.source.resolved(),
+            resolved,
+            // A clone has no single downloaded archive to hash.
+            integrity: String::new(),
+            method: self.method_name().to_string(),
+            dir: gr.repo.clone(),
+        })
+    }
+
+    async fn install_tarball(
+        &self,
+        path: PathBuf,
+        s: sync::Sender<InstallState>,
+        expected: Option<ExpectedPin>,
+        cache: &RefCache,
+    ) -> Result<LockEntry> {
+        use anyhow::Context;
 
-        if &b"404: Not Found\n" == &recv_bytes.as_slice() {
-            bail!("plugin does not exist (404)");
+        let name = self.to_string();
+
+        if let Source::Git(GitRepo {
+            provider: GitProvider::Generic(_),
+            ..
+        }) = &self.source
+        {
+            bail!("self-hosted Git hosts have no archive endpoint; use ‘method: clone’");
+        }
+
+        // Resolve the ref (querying the provider for the default branch when none was given) before
+        // building the download URL.
+        let resolved = match &self.source {
+            Source::Git(gr) => {
+                // Resolve the ref to a concrete commit (skipping the lookup when it is already a
+                // hash) so the lockfile records a hash like the clone backend does and a moved
+                // branch or tag is detectable.
+                gr.resolve_commit(cache).await?
+            }
+            Source::Archive(a) => a.to_string(),
+        };
+        let url = match &self.source {
+            Source::Git(gr) => gr.archive_url(&resolved)?,
+            Source::Archive(a) => (*a).clone(),
+        };
+        let url = url.as_str();
+
+        // For a Git plugin whose ref resolved to a commit, the download URL embeds that hash, so its
+        // bytes are immutable and the content-addressed cache is safe to consult on every install,
+        // including pinned ones — this is exactly the near-instant reinstall the cache is for. The
+        // cache is consulted for every URL alike: one built from a literal ref (after a failed
+        // commit lookup) or a bare archive URL is served from the cache once stored. Tamper-evidence is preserved
+        // regardless: ‘cache_lookup’ re-verifies a cache entry against its stored digest, and the
+        // integrity pin below is checked against the served bytes whether they came from the cache
+        // or the network.
+        let key = url_cache_key(url);
+        let (recv_bytes, from_cache) = match cache_lookup(&key).await {
+            Some(bytes) => {
+                s.send(InstallState {
+                    status: InstallStateKind::Cached,
+                    name: name.clone(),
+                })
+                .await;
+
+                (bytes, true)
+            }
+            None => {
+                s.send(InstallState {
+                    status: InstallStateKind::Downloading,
+                    name: name.clone(),
+                })
+                .await;
+
+                let bytes = recv_bytes_retry(url, &s, &name)
+                    .await
+                    .with_context(|| "failed downloading plugin")?;
+
+                if &b"404: Not Found\n" == &bytes.as_slice() {
+                    bail!("plugin does not exist (404)");
+                }
+
+                (bytes, false)
+            }
+        };
+
+        // Hash the archive before touching the filesystem so that a moved tag or a tampered
+        // download is caught against the pin recorded in ‘strand.lock’.
+        let integrity = integrity_digest(&recv_bytes);
+
+        // Only populate the cache once the freshly downloaded bytes have hashed successfully.
+        if !from_cache {
+            cache_store(&key, &recv_bytes, &integrity).await?;
+        }
+
+        // Enforce the integrity pin only when we re-downloaded the exact commit it was recorded
+        // against. A bumped ref or an advanced branch resolves to a different commit and is simply
+        // recorded as a fresh pin below; a mismatch on the same commit means the archive really
+        // changed underneath us.
+        if let Some(pin) = &expected {
+            if pin.resolved == resolved && pin.integrity != integrity {
+                bail!(
+                    "integrity mismatch for {}: expected {}, got {} -- \
+                     the upstream archive was tampered with or its tag was moved",
+                    name,
+                    pin.integrity,
+                    integrity,
+                );
+            }
+        }
+
+        // Only populate the cache once the freshly downloaded bytes have passed the integrity pin.
+        if !from_cache {
+            cache_store(&key, &recv_bytes, &integrity).await?;
         }
 
         s.send(InstallState {
@@ -337,17 +957,198 @@ impl Plugin {
         })
         .await;
 
-        decompress_tar_gz(&recv_bytes, &path)
+        let extracted = decompress_tar_gz(&recv_bytes, &path)
             .with_context(|| "failed to extract plugin archive")?;
 
+        // Run the post-install build hook, if one was configured, from inside the plugin’s tree.
+        // A non-zero exit aborts the install just like a failed download would.
+        if let Some(build) = &self.build {
+            use std::process::Command;
+
+            s.send(InstallState {
+                status: InstallStateKind::Building,
+                name: name.clone(),
+            })
+            .await;
+
+            let output = Command::new("sh")
+                .arg("-c")
+                .arg(build)
+                .current_dir(&extracted)
+                .output()
+                .with_context(|| format!("failed to spawn build command for {}", name))?;
+
+            if !output.status.success() {
+                bail!(
+                    "build command for {} exited with {}:\n{}",
+                    name,
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr),
+                );
+            }
+        }
+
         s.send(InstallState {
             status: InstallStateKind::Installed,
             name,
         })
         .await;
 
-        Ok(())
+        Ok(LockEntry {
+            spec: self.source.resolved(),
+            resolved,
+            integrity,
+            method: self.method_name().to_string(),
+            dir: extracted
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        })
+    }
+}
+
+/// A single plugin’s entry in ‘strand.lock’: the configured ref spec it was installed from (used
+/// by incremental updates to detect a changed ref), the commit hash that spec resolved to, a
+/// [Subresource Integrity](https://www.w3.org/TR/SRI/) digest of the downloaded archive, the
+/// acquisition method (so a switch between backends prunes the old directory), and the directory it
+/// was installed into (so incremental updates can prune it individually).
+#[derive(Serialize, Deserialize)]
+pub struct LockEntry {
+    pub spec: String,
+    pub resolved: String,
+    pub integrity: String,
+    // Defaulted so lockfiles written before this field existed still parse.
+    #[serde(default)]
+    pub method: String,
+    pub dir: String,
+}
+
+/// The lockfile maps each plugin’s fully-qualified identity (provider/user/repo, or the URL of a
+/// bare archive) to its pinned [`LockEntry`].
+pub type Lockfile = std::collections::BTreeMap<String, LockEntry>;
+
+// Whether a ref is a full 40-character commit hash. Such refs are immutable, so they need no
+// default-branch or commit-metadata lookup and are trusted not to move.
+fn is_commit_hash(s: &str) -> bool {
+    s.len() == 40 && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn integrity_digest(bytes: &[u8]) -> String {
+    use sha2::{Digest, Sha512};
+
+    format!("sha512-{}", base64::encode(Sha512::digest(bytes)))
+}
+
+fn get_lockfile_path() -> PathBuf {
+    get_config_dir().join("strand.lock")
+}
+
+fn get_cache_dir() -> PathBuf {
+    get_config_dir().join("cache")
+}
+
+// The log file for a given plugin, with any path separators in its identity flattened so the name
+// is a single file.
+fn get_log_path(name: &str) -> PathBuf {
+    let file = name.replace('/', "-").replace('\\', "-");
+    get_config_dir().join("log").join(format!("{}.log", file))
+}
+
+// Append a timestamped line to a plugin’s log file. Logging is best-effort: a failure to write the
+// log should never fail an install.
+fn append_log(path: &Path, line: &str) {
+    use std::fs::OpenOptions;
+    use std::io::Write;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
     }
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+        let _ = writeln!(file, "[{}] {}", timestamp, line);
+    }
+}
+
+// Cache entries are keyed by the SHA-256 of the resolved download URL, rendered as hex so it is a
+// safe filename.
+fn url_cache_key(url: &str) -> String {
+    use sha2::{Digest, Sha256};
+
+    Sha256::digest(url.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+async fn cache_lookup(key: &str) -> Option<Vec<u8>> {
+    use async_std::fs;
+
+    let dir = get_cache_dir();
+    let bytes = fs::read(dir.join(format!("{}.tar.gz", key))).await.ok()?;
+
+    // Verify the cached bytes against the integrity digest recorded when they were stored, so a
+    // corrupted or tampered cache file is treated as a miss rather than served blindly.
+    let stored = fs::read_to_string(dir.join(format!("{}.sri", key)))
+        .await
+        .ok()?;
+    if integrity_digest(&bytes) == stored.trim() {
+        Some(bytes)
+    } else {
+        None
+    }
+}
+
+async fn cache_store(key: &str, bytes: &[u8], integrity: &str) -> Result<()> {
+    use async_std::fs;
+
+    let dir = get_cache_dir();
+    fs::create_dir_all(&dir).await?;
+
+    // Write to a temporary file and rename it into place so a cache entry is never observed
+    // half-written.
+    let final_path = dir.join(format!("{}.tar.gz", key));
+    let tmp_path = dir.join(format!("{}.tar.gz.tmp", key));
+    fs::write(&tmp_path, bytes).await?;
+    fs::rename(&tmp_path, &final_path).await?;
+
+    // Record the integrity digest of the cached bytes alongside them.
+    fs::write(dir.join(format!("{}.sri", key)), integrity).await?;
+
+    Ok(())
+}
+
+pub async fn clean_cache() -> Result<()> {
+    let dir = get_cache_dir();
+
+    if dir.exists() {
+        remove_path(&dir).await?;
+    }
+
+    Ok(())
+}
+
+pub async fn get_lockfile(lock_file: &Path) -> Result<Lockfile> {
+    use async_std::fs;
+
+    // A missing lockfile simply means nothing has been installed yet.
+    match fs::read_to_string(lock_file).await {
+        Ok(contents) => Ok(yaml::from_str(&contents)?),
+        Err(_) => Ok(Lockfile::new()),
+    }
+}
+
+pub async fn write_lockfile(lock_file: &Path, lock: &Lockfile) -> Result<()> {
+    use async_std::fs;
+
+    fs::write(lock_file, yaml::to_string(lock)?).await?;
+
+    Ok(())
 }
 
 #[derive(Deserialize)]
@@ -366,24 +1167,66 @@ pub async fn get_config(config_file: &Path) -> Result<Config> {
     Ok(config)
 }
 
-fn decompress_tar_gz(bytes: &[u8], path: &Path) -> Result<()> {
+fn decompress_tar_gz(bytes: &[u8], path: &Path) -> Result<PathBuf> {
     use flate2::read::GzDecoder;
+    use std::path::Component;
     use tar::Archive;
 
     let tar = GzDecoder::new(bytes);
     let mut archive = Archive::new(tar);
-    archive.unpack(path)?;
 
-    Ok(())
+    // Unpack entry by entry, noting the top-level directory the archive expands into so that build
+    // hooks have a concrete working directory to run in.
+    let mut root = None;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let entry_path = entry.path()?.into_owned();
+
+        if root.is_none() {
+            if let Some(Component::Normal(first)) = entry_path.components().next() {
+                root = Some(path.join(first));
+            }
+        }
+
+        entry.unpack_in(path)?;
+    }
+
+    Ok(root.unwrap_or_else(|| path.to_path_buf()))
 }
 
 pub async fn install_plugins(plugins: Vec<Plugin>, dir: PathBuf) -> Result<()> {
     use pbr::MultiBar;
 
+    async_std::fs::create_dir_all(&dir).await?;
+
+    let lock_path = get_lockfile_path();
+    let existing_lock = get_lockfile(&lock_path).await?;
+
+    // Shared across every task so that a repo’s default branch is resolved at most once across
+    // these installs.
+    let ref_cache: RefCache = std::sync::Arc::new(sync::Mutex::new(std::collections::HashMap::new()));
+
     let mut tasks = Vec::with_capacity(plugins.len());
     let mut multi = MultiBar::new(); // Holds the spinners of all plugins
 
     plugins.into_iter().for_each(|p| {
+        // If we have installed this plugin before at the same configured ref, carry its recorded
+        // commit and integrity digest through so the fresh download can be checked against them.
+        // A changed ref spec is treated as a new pin so a deliberate version bump does not fail as
+        // ‘tampered’.
+        let expected = existing_lock
+            .get(&p.identity())
+            .filter(|e| e.spec == p.source.resolved() && !e.integrity.is_empty())
+            .map(|e| ExpectedPin {
+                resolved: e.resolved.clone(),
+                integrity: e.integrity.clone(),
+            });
+        let ref_cache = ref_cache.clone();
+
+        // Each plugin keeps its own log file recording every state transition for post-mortems.
+        let log_path = get_log_path(&p.identity());
+        let ticker_log_path = log_path.clone();
+
         // We have to make a fresh clone of ‘dir’ for each plugin so that the task’s future stays
         // 'static.
         let dir = dir.clone();
@@ -408,6 +1251,7 @@ pub async fn install_plugins(plugins: Vec<Plugin>, dir: PathBuf) -> Result<()> {
                 loop {
                     if r.is_full() {
                         let install_state = r.recv().await.unwrap();
+                        append_log(&ticker_log_path, &install_state.status.log_line());
                         let msg = format!("{}  ", install_state);
 
                         if let InstallStateKind::Installed | InstallStateKind::Error(_) =
@@ -426,32 +1270,147 @@ pub async fn install_plugins(plugins: Vec<Plugin>, dir: PathBuf) -> Result<()> {
             });
 
             // If the plugin install fails we send the error that occurred to the spinner for
-            // display to the user.
+            // display to the user; on success we hand back the lock entry to record.
             let install = task::spawn(async move {
-                if let Err(e) = p.install(dir, s.clone()).await {
-                    s.send(InstallState {
-                        status: InstallStateKind::Error(e),
-                        name: p.to_string(),
-                    })
-                    .await;
+                match p.install(dir, s.clone(), expected, &ref_cache).await {
+                    Ok(entry) => Ok((p.identity(), entry)),
+                    Err(e) => {
+                        s.send(InstallState {
+                            status: InstallStateKind::Error(e),
+                            name: p.to_string(),
+                        })
+                        .await;
+                        Err(log_path)
+                    }
                 }
             });
 
             ticker.await;
-            install.await;
+            install.await
         }));
     });
 
     // Start listening for spinner activity just before the plugins’ installation is commenced.
     multi.listen();
 
+    // Start from the previous lockfile so that plugins we did not touch this run keep their pins,
+    // then overwrite each entry we successfully (re)installed.
+    let mut lock = existing_lock;
+    let mut failures = Vec::new();
     for task in tasks {
-        task.await;
+        match task.await {
+            Ok((name, entry)) => {
+                lock.insert(name, entry);
+            }
+            Err(log_path) => failures.push(log_path),
+        }
+    }
+
+    write_lockfile(&lock_path, &lock).await?;
+
+    // Point the user at the log of each failed install so they can see exactly what happened.
+    for log_path in &failures {
+        eprintln!("An install failed; see {} for details.", log_path.display());
+    }
+
+    Ok(())
+}
+
+pub async fn remove_path(path: &Path) -> Result<()> {
+    use async_std::fs;
+
+    if fs::metadata(path).await?.is_dir() {
+        fs::remove_dir_all(path).await?;
+    } else {
+        fs::remove_file(path).await?;
     }
 
     Ok(())
 }
 
+/// Bring the plugin directory in line with the config without wiping it: drop plugins that left the
+/// config, and only (re)install plugins that need it. A plugin is refetched when it is new, when
+/// its directory is missing, when its configured ref spec or backend changed, or — for a *moving*
+/// ref (a branch, tag or the default branch, i.e. anything that is not a full commit hash) — when
+/// re-resolving it yields a commit different from the locked one. A fully commit-pinned plugin is
+/// immutable and so is trusted and skipped once installed; re-resolving moving refs is what keeps
+/// an advanced branch, a moved tag or a shifted default branch from going unnoticed.
+pub async fn update_plugins(plugins: Vec<Plugin>, dir: PathBuf) -> Result<()> {
+    use std::collections::HashSet;
+
+    let lock_path = get_lockfile_path();
+    let mut lock = get_lockfile(&lock_path).await?;
+
+    // Shared so a repo’s default branch is resolved at most once while reconciling; commit
+    // lookups are not memoised.
+    let ref_cache: RefCache =
+        std::sync::Arc::new(sync::Mutex::new(std::collections::HashMap::new()));
+
+    let configured: HashSet<String> = plugins.iter().map(|p| p.identity()).collect();
+
+    // Remove plugins that are still installed but no longer configured, one directory at a time.
+    let stale: Vec<String> = lock
+        .keys()
+        .filter(|name| !configured.contains(*name))
+        .cloned()
+        .collect();
+    for name in stale {
+        if let Some(entry) = lock.remove(&name) {
+            let path = dir.join(&entry.dir);
+            if path.exists() {
+                remove_path(&path).await?;
+            }
+        }
+    }
+
+    // Keep only the plugins that actually need fetching: new ones, ones whose configured ref spec
+    // or backend changed, ones whose extracted directory has gone missing, and moving refs whose
+    // current commit differs from the locked one. The spec check compares the stored ref spec (the
+    // same representation ‘Source::resolved’ yields), so commit-pinned plugins aren’t needlessly
+    // refetched every run regardless of backend.
+    let mut to_install = Vec::new();
+    for p in plugins {
+        match lock.get(&p.identity()) {
+            Some(entry) => {
+                let spec = p.source.resolved();
+                let spec_changed = entry.spec != spec;
+                // A backend switch (tarball <-> clone) installs into a differently-named directory;
+                // the empty string means the entry predates this field, so don’t treat it as a
+                // change.
+                let method_changed =
+                    !entry.method.is_empty() && entry.method != p.method_name();
+                let missing = !dir.join(&entry.dir).exists();
+                // For a moving ref whose spec is unchanged, re-resolve the commit and compare it to
+                // the locked one so an advanced branch, a moved tag or a shifted default branch is
+                // picked up. A fully commit-pinned spec is immutable and needs no lookup.
+                let moved = !spec_changed
+                    && !is_commit_hash(&spec)
+                    && matches!(
+                        p.source.resolve_current_commit(&ref_cache).await,
+                        Some(commit) if commit != entry.resolved
+                    );
+                // A ref change, a backend switch or a moved ref installs into a new directory, so
+                // prune the old one first; otherwise it lingers on ‘runtimepath’ forever.
+                if spec_changed || method_changed || moved {
+                    let old = dir.join(&entry.dir);
+                    if old.exists() {
+                        remove_path(&old).await?;
+                    }
+                }
+                if spec_changed || method_changed || missing || moved {
+                    to_install.push(p);
+                }
+            }
+            None => to_install.push(p),
+        }
+    }
+
+    // Persist the pruned lockfile before installing so ‘install_plugins’ merges onto it.
+    write_lockfile(&lock_path, &lock).await?;
+
+    install_plugins(to_install, dir).await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -475,4 +1434,79 @@ mod tests {
             home_dir.join("bar/baz/quux/foo.txt")
         );
     }
+
+    #[test]
+    fn integrity_digest_is_sri_sha512() {
+        // The SRI digest of the empty input is a fixed, well-known value, so a regression in the
+        // format (prefix, hash, or base64 alphabet) is caught.
+        assert_eq!(
+            integrity_digest(b""),
+            "sha512-z4PhNX7vuL3xVChQ1m2AB9Yg5AULVxXcg/SpIdNs6c5H0NE8XYXysP+DGNKHfuwvY7kxvUdBeoGlODJ6+SfaPg=="
+        );
+    }
+
+    #[test]
+    fn integrity_digest_depends_on_bytes() {
+        assert_ne!(integrity_digest(b"one"), integrity_digest(b"two"));
+        assert_eq!(integrity_digest(b"same"), integrity_digest(b"same"));
+    }
+
+    #[test]
+    fn url_cache_key_is_hex_sha256() {
+        let key = url_cache_key("https://example.com/a.tar.gz");
+        assert_eq!(key.len(), 64);
+        assert!(key.chars().all(|c| c.is_ascii_hexdigit()));
+    }
+
+    #[test]
+    fn identity_disambiguates_shared_repo_names() {
+        let a = GitRepo::from_str("alice/vim-surround").unwrap();
+        let b = GitRepo::from_str("bob/vim-surround").unwrap();
+        assert_ne!(a.identity(), b.identity());
+        assert_eq!(a.identity(), "github/alice/vim-surround");
+    }
+
+    #[test]
+    fn source_resolved_reflects_configured_ref() {
+        let pinned = Source::Git(GitRepo::from_str("user/repo:v1.2.3").unwrap());
+        assert_eq!(pinned.resolved(), "v1.2.3");
+
+        // An elided ref has no stable identity until install time, so it compares as empty.
+        let elided = Source::Git(GitRepo::from_str("user/repo").unwrap());
+        assert_eq!(elided.resolved(), "");
+    }
+
+    #[test]
+    fn git_repo_elides_missing_ref() {
+        // With no ‘:ref’ the reference is left unspecified, to be resolved to the repository’s
+        // real default branch at install time rather than defaulting to ‘master’.
+        let elided = GitRepo::from_str("user/repo").unwrap();
+        assert!(elided.git_ref.is_none());
+
+        let pinned = GitRepo::from_str("user/repo:main").unwrap();
+        assert_eq!(pinned.git_ref.as_deref(), Some("main"));
+    }
+
+    #[test]
+    fn plugin_parses_build_hook_and_method() {
+        let p: Plugin = yaml::from_str("Git: user/repo\nbuild: make\nmethod: clone").unwrap();
+        assert_eq!(p.build.as_deref(), Some("make"));
+        assert!(matches!(p.method, Method::Clone));
+    }
+
+    #[test]
+    fn plugin_build_accepts_do_and_run_aliases() {
+        let with_do: Plugin = yaml::from_str("Git: user/repo\ndo: ./install.sh").unwrap();
+        assert_eq!(with_do.build.as_deref(), Some("./install.sh"));
+
+        let with_run: Plugin = yaml::from_str("Git: user/repo\nrun: ./install.sh").unwrap();
+        assert_eq!(with_run.build.as_deref(), Some("./install.sh"));
+    }
+
+    #[test]
+    fn bare_plugin_defaults_to_tarball_without_build() {
+        let p: Plugin = yaml::from_str("Git: user/repo").unwrap();
+        assert!(p.build.is_none());
+        assert!(matches!(p.method, Method::Tarball));
+    }
 }
@@ -1,6 +1,4 @@
 use anyhow::Result;
-use async_std::fs;
-use std::path::Path;
 use strand::Plugin;
 use structopt::StructOpt;
 
@@ -10,6 +8,10 @@ struct Opts {
     #[structopt(long)]
     config_location: bool,
 
+    /// Empties the download cache and exits
+    #[structopt(long)]
+    clear_cache: bool,
+
     #[structopt(subcommand)]
     subcommand: Option<Subcommand>,
 }
@@ -24,6 +26,24 @@ enum Subcommand {
         #[structopt(name = "PLUGINS", required = true)]
         plugins: Vec<Plugin>,
     },
+
+    /// Incrementally install, update or remove plugins to match the config file
+    #[structopt(name = "update")]
+    Update,
+
+    /// Manage the content-addressed download cache
+    #[structopt(name = "cache")]
+    Cache {
+        #[structopt(subcommand)]
+        cache: CacheSubcommand,
+    },
+}
+
+#[derive(StructOpt)]
+enum CacheSubcommand {
+    /// Remove all cached archives
+    #[structopt(name = "clean")]
+    Clean,
 }
 
 #[async_std::main]
@@ -40,6 +60,19 @@ async fn main() -> Result<()> {
         return Ok(());
     }
 
+    // Cache maintenance needs no config file, so handle it before loading one.
+    if opts.clear_cache {
+        strand::clean_cache().await?;
+        return Ok(());
+    }
+
+    if let Some(Subcommand::Cache { cache }) = &opts.subcommand {
+        match cache {
+            CacheSubcommand::Clean => strand::clean_cache().await?,
+        }
+        return Ok(());
+    }
+
     let config = strand::get_config(&config_path).await?;
 
     // Install all plugins specified by the install subcommand.
@@ -48,29 +81,9 @@ async fn main() -> Result<()> {
         return Ok(()); // Early return since we don’t need to install plugins from the config file.
     }
 
-    // Clean out the plugin directory before installing.
-    ensure_empty_dir(&config.plugin_dir).await?;
-    strand::install_plugins(config.plugins, config.plugin_dir).await?;
-
-    Ok(())
-}
-
-async fn remove_path(path: &Path) -> Result<()> {
-    if fs::metadata(path).await?.is_dir() {
-        fs::remove_dir_all(path).await?;
-    } else {
-        fs::remove_file(path).await?;
-    }
-
-    Ok(())
-}
-
-async fn ensure_empty_dir(path: &Path) -> Result<()> {
-    if path.exists() {
-        remove_path(path).await?;
-    }
-
-    fs::create_dir_all(path).await?;
+    // Both the explicit ‘update’ subcommand and the default run reconcile the plugin directory
+    // with the config incrementally rather than wiping and redownloading everything.
+    strand::update_plugins(config.plugins, config.plugin_dir).await?;
 
     Ok(())
 }